@@ -9,38 +9,60 @@
 //! A few distinct lists of APKs are used.  AndroidRank compiles the most popular apps available on
 //! the Google Play Store.  You can also specify a CSV file which lists the apps to download.  If
 //! you have a simple file with one app ID per line, you can just treat it as a CSV with a single
-//! field.
+//! field.  `Search` resolves a list from a Google Play search query, and `Category` resolves one
+//! from a Play Store top-charts category; both log in to Google Play the same way the
+//! `GooglePlay` download source does.
 //!
 //! # Download Sources
 //!
 //! You can use this tool to download from a few distinct sources.
 //!
 //! * The Google Play Store, given a username and password.
-//! * APKPure, a third-party site hosting APKs available on the Play Store.  You must be running
-//! an instance of the ChromeDriver for this to work, since a headless browser is used.
+//! * APKPure, a third-party site hosting APKs available on the Play Store.  This spawns and
+//! manages its own headless Chrome instance, so no external service needs to be running.
 //! either from the Google Play Store directly, given a username
+//!
+//! # Modes
+//!
+//! By default (`-m download`) the resolved list is downloaded.  `-m verify` ignores the list and
+//! instead re-hashes every file recorded in `OUTPUT`'s `manifest.json`, reporting any that are
+//! missing, the wrong size, or corrupt, with a nonzero exit code if any check fails.  `-m
+//! list-missing` resolves the list as usual but only prints the app IDs that don't have a valid
+//! manifest entry yet, so an interrupted run can be resumed.  `-m url` resolves metadata only
+//! (version, size, download URL) for each app and prints one JSON record per line to stdout,
+//! without downloading anything, so the tool can be composed into other pipelines.
 
 #[macro_use]
 extern crate clap;
 
+mod manifest;
+mod progress;
+mod retry;
+
 use clap::{App, Arg};
 use futures_util::StreamExt;
 use gpapi::error::{Error as GpapiError, ErrorKind};
 use gpapi::Gpapi;
+use headless_chrome::protocol::cdp::Page::SetDownloadBehavior;
+use headless_chrome::{Browser, LaunchOptions};
+use indicatif::ProgressBar;
+use manifest::VerifyStatus;
 use regex::Regex;
 use serde_json::json;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
 use std::rc::Rc;
-use std::time::Duration;
-use thirtyfour::prelude::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 arg_enum! {
     #[derive(Debug)]
     pub enum ListSource {
         AndroidRank,
         CSV,
+        Search,
+        Category,
     }
 }
 arg_enum! {
@@ -64,6 +86,32 @@ fn fetch_csv_list(csv: &str, field: usize) -> Result<Vec<String>, Box<dyn Error>
     Ok(parse_csv_text(fs::read_to_string(csv)?, field))
 }
 
+/// Resolves a list of app IDs from gpapi's search endpoint for `query`, reusing an
+/// already-authenticated session.
+async fn fetch_google_play_search_list(gpa: &Gpapi, query: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let results = gpa.search(query).await?;
+    Ok(results.into_iter().map(|app| app.doc_id).collect())
+}
+
+/// Resolves a list of app IDs from the Play Store's top charts for `category`, reusing an
+/// already-authenticated session.
+async fn fetch_google_play_category_list(gpa: &Gpapi, category: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let results = gpa.list(category, None).await?;
+    Ok(results.into_iter().map(|app| app.doc_id).collect())
+}
+
+/// Returns the run's shared, already-logged-in `Gpapi` session, logging in on first use so
+/// list resolution (`Search`/`Category`) and the `GooglePlay` download/url paths share one
+/// authenticated session instead of each performing their own login.
+async fn ensure_google_play_session(session: &mut Option<Rc<Gpapi>>, username: &str, password: &str) -> Rc<Gpapi> {
+    if session.is_none() {
+        let mut gpa = Gpapi::new("en_US", "UTC", "hero2lte");
+        gpa.login(username, password).await.expect("Could not log in to google play");
+        *session = Some(Rc::new(gpa));
+    }
+    Rc::clone(session.as_ref().unwrap())
+}
+
 fn parse_csv_text(text: String, field: usize) -> Vec<String> {
     let field = field - 1;
     text.split("\n").filter_map(|l| {
@@ -77,120 +125,253 @@ fn parse_csv_text(text: String, field: usize) -> Vec<String> {
     }).collect()
 }
 
-async fn download_apps_from_google_play(app_ids: Vec<String>, parallel: usize, username: &str, password: &str, outpath: &str) {
-    let mut gpa = Gpapi::new("en_US", "UTC", "hero2lte");
-    gpa.login(username, password).await.expect("Could not log in to google play");
-    let gpa = Rc::new(gpa);
+async fn download_apps_from_google_play(app_ids: Vec<String>, parallel: usize, gpa: Rc<Gpapi>, outpath: &str, retries: u32, retry_delay: Duration) {
+    let reporter = progress::ProgressReporter::new();
 
-    futures_util::stream::iter(
+    let results = futures_util::stream::iter(
         app_ids.into_iter().map(|app_id| {
             let gpa = Rc::clone(&gpa);
+            // gpapi's `download()` doesn't expose a byte-level progress hook, so this renders
+            // as a spinner rather than a bar that would otherwise never move.
+            let bar = reporter.spinner(&app_id);
             async move {
-                println!("Downloading {}...", app_id);
-                match gpa.download(&app_id, None, &Path::new(outpath)).await {
-                    Ok(_) => Ok(()),
+                let retry_bar = bar.clone();
+                let result = retry::retry_with_backoff(
+                    retries,
+                    retry_delay,
+                    |err: &GpapiError| !matches!(err.kind(), ErrorKind::FileExists | ErrorKind::InvalidApp),
+                    |attempt| progress::mark_retrying(&retry_bar, attempt - 1),
+                    || {
+                        let gpa = Rc::clone(&gpa);
+                        let app_id = app_id.clone();
+                        async move { gpa.download(&app_id, None, &Path::new(outpath)).await }
+                    },
+                ).await;
+
+                match result {
+                    Ok(_) => {
+                        record_google_play_download(&app_id, outpath);
+                        progress::mark_done(&bar);
+                        Ok(())
+                    }
                     Err(err) if matches!(err.kind(), ErrorKind::FileExists) => {
-                        println!("File already exists for {}.  Aborting.", app_id);
+                        record_google_play_download(&app_id, outpath);
+                        bar.finish_with_message("already exists");
                         Ok(())
                     }
-                    Err(err) if matches!(err.kind(), ErrorKind::InvalidApp) => {
-                        println!("Invalid app response for {}.  Aborting.", app_id);
+                    Err(err) => {
+                        progress::mark_failed(&bar);
                         Err(err)
                     }
-                    Err(_) => {
-                        println!("An error has occurred attempting to download {}.  Retry #1...", app_id);
-                        match gpa.download(&app_id, None, &Path::new(outpath)).await {
-                            Ok(_) => Ok(()),
-                            Err(_) => {
-                                println!("An error has occurred attempting to download {}.  Retry #2...", app_id);
-                                match gpa.download(&app_id, None, &Path::new(outpath)).await {
-                                    Ok(_) => Ok(()),
-                                    Err(err) => {
-                                        println!("An error has occurred attempting to download {}.  Aborting.", app_id);
-                                        Err(err)
-                                    }
-                                }
-                            }
-                        }
-                    }
                 }
             }
         })
     ).buffer_unordered(parallel).collect::<Vec<Result<(), GpapiError>>>().await;
+
+    let failed = results.iter().filter(|r| r.is_err()).count();
+    println!("{}/{} apps downloaded successfully", results.len() - failed, results.len());
 }
 
-async fn download_apps_from_apkpure(app_ids: Vec<String>, parallel: usize, outpath: &str) -> WebDriverResult<()> {
+/// Resolves metadata for each app via gpapi's details lookup and prints one JSON record per
+/// line to stdout, without downloading anything.
+async fn print_google_play_metadata(app_ids: Vec<String>, gpa: &Gpapi) -> Result<(), Box<dyn Error>> {
+    for app_id in app_ids {
+        match gpa.details(&app_id).await {
+            Ok(details) => {
+                println!("{}", json!({
+                    "app_id": app_id,
+                    "version_code": details.version_code,
+                    "size": details.size,
+                    "download_url": details.download_url,
+                    "source": "GooglePlay",
+                }));
+            }
+            Err(err) => {
+                eprintln!("Could not fetch metadata for {}: {}", app_id, err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Hashes the APK gpapi just saved for `app_id` and appends it to `outpath`'s manifest.
+/// gpapi saves split APKs as `.xapk` rather than `.apk`, so both extensions are tried.
+fn record_google_play_download(app_id: &str, outpath: &str) {
+    let candidates = [format!("{}.apk", app_id), format!("{}.xapk", app_id)];
+    let filename = candidates.iter().find(|filename| Path::new(outpath).join(filename).is_file());
+
+    match filename {
+        Some(filename) => {
+            if let Err(err) = manifest::record_download(outpath, app_id, filename, "GooglePlay") {
+                println!("Could not record manifest entry for {}: {}", app_id, err);
+            }
+        }
+        None => {
+            println!("Could not record manifest entry for {}: no .apk or .xapk file found in {}", app_id, outpath);
+        }
+    }
+}
+
+async fn download_apps_from_apkpure(app_ids: Vec<String>, parallel: usize, outpath: &str, retries: u32, retry_delay: Duration) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let browser = Arc::new(Browser::new(LaunchOptions::default_builder().headless(true).build()?)?);
+    let reporter = progress::ProgressReporter::new();
+    let total = app_ids.len();
+
     let fetches = futures_util::stream::iter(
         app_ids.into_iter().map(|app_id| {
+            let browser = Arc::clone(&browser);
+            let bar = reporter.spinner(&app_id);
             async move {
-                match download_single_app(&app_id, outpath).await {
-                    Ok(res_tuple) => futures_util::future::ready(Some(res_tuple)),
+                let retry_bar = bar.clone();
+                let result = retry::retry_with_backoff(
+                    retries,
+                    retry_delay,
+                    |_err: &Box<dyn Error + Send + Sync>| true,
+                    |attempt| progress::mark_retrying(&retry_bar, attempt - 1),
+                    || download_single_app(&browser, &app_id, outpath),
+                ).await;
+
+                match result {
+                    Ok(res_tuple) => Some((res_tuple, bar)),
                     Err(_) => {
-                        println!("An error has occurred attempting to download {}.  Retry #1...", app_id);
-                        match download_single_app(&app_id, outpath).await {
-                            Ok(res_tuple) => futures_util::future::ready(Some(res_tuple)),
-                            Err(_) => {
-                                println!("An error has occurred attempting to download {}.  Retry #2...", app_id);
-                                match download_single_app(&app_id, outpath).await {
-                                    Ok(res_tuple) => futures_util::future::ready(Some(res_tuple)),
-                                    Err(_) => {
-                                        println!("An error has occurred attempting to download {}.  Aborting.", app_id);
-                                        futures_util::future::ready(None)
-                                    }
-                                }
-                            }
-                        }
+                        progress::mark_failed(&bar);
+                        None
                     }
                 }
             }
         })
-    ).buffer_unordered(parallel).filter_map(|i| i).collect::<Vec<(String, String, String)>>();
-    println!("Waiting...");
+    ).buffer_unordered(parallel).filter_map(futures_util::future::ready).collect::<Vec<((String, String, String), ProgressBar)>>();
     let results = fetches.await;
-    for move_file in results {
+    let mut saved = 0;
+    for (move_file, bar) in &results {
         if let Ok(paths) = fs::read_dir(&move_file.0) {
             let dir_list = paths.filter_map(|path| path.ok()).collect::<Vec<fs::DirEntry>>();
             if dir_list.len() > 0 {
-                println!("Saving {}...", move_file.2);
                 let old_filename = dir_list[0].file_name();
-                fs::rename(Path::new(&move_file.0).join(old_filename), Path::new(&move_file.0).join(move_file.1)).unwrap();
+                fs::rename(Path::new(&move_file.0).join(old_filename), Path::new(&move_file.0).join(&move_file.1)).unwrap();
+                let relative_filename = format!("{}/{}", move_file.2, move_file.1);
+                if let Err(err) = manifest::record_download(outpath, &move_file.2, &relative_filename, "APKPure") {
+                    println!("Could not record manifest entry for {}: {}", move_file.2, err);
+                }
+                progress::mark_done(bar);
+                saved += 1;
             } else {
-                println!("Could not save {}...", move_file.2);
+                bar.finish_with_message("could not save");
             }
         } else {
-            println!("Could not save {}...", move_file.2);
+            bar.finish_with_message("could not save");
         }
     }
+    println!("{}/{} apps downloaded successfully", saved, total);
     Ok(())
 }
 
-async fn download_single_app(app_id: &str, outpath: &str) -> WebDriverResult<(String, String, String)> {
-    println!("Downloading {}...", app_id);
+async fn download_single_app(browser: &Arc<Browser>, app_id: &str, outpath: &str) -> Result<(String, String, String), Box<dyn Error + Send + Sync>> {
+    let browser = Arc::clone(browser);
+    let app_id = app_id.to_string();
+    let outpath = outpath.to_string();
+
+    tokio::task::spawn_blocking(move || fetch_single_app(&browser, &app_id, &outpath)).await?
+}
+
+/// Drives a single tab through the APKPure download flow.  `headless_chrome`'s API is
+/// synchronous, so this runs on a blocking task and is not called directly from async code.
+fn fetch_single_app(browser: &Browser, app_id: &str, outpath: &str) -> Result<(String, String, String), Box<dyn Error + Send + Sync>> {
     let app_url = format!("https://apkpure.com/a/{}/download?from=details", app_id);
-    let mut caps = DesiredCapabilities::chrome();
-    let filepath = format!("{}", Path::new(outpath).join(app_id.clone()).to_str().unwrap());
-    let prefs = json!({
-        "download.default_directory": filepath
-    });
-    caps.add_chrome_option("prefs", prefs).unwrap();
-
-    let driver = match WebDriver::new("http://localhost:4444", &caps).await {
-        Ok(driver) => driver,
-        Err(_) => panic!("chromedriver must be running on port 4444")
-    };
-    let delay = Duration::new(10, 0);
-    driver.set_implicit_wait_timeout(delay).await?;
-    driver.get(app_url).await?;
-    let elem_result = driver.find_element(By::Css("span.file")).await?;
+    let filepath = Path::new(outpath).join(app_id).to_str().unwrap().to_string();
+    fs::create_dir_all(&filepath)?;
+
+    let tab = browser.new_tab()?;
+    tab.call_method(SetDownloadBehavior {
+        behavior: "allow".to_string(),
+        download_path: Some(filepath.clone()),
+    })?;
+    tab.navigate_to(&app_url)?;
+    tab.wait_until_navigated()?;
+    let elem_result = tab.wait_for_element("span.file")?;
     let re = Regex::new(r" \([0-9.]+ MB\)$").unwrap();
 
-    let new_filename = elem_result.text().await?;
+    let new_filename = elem_result.get_inner_text()?;
     let new_filename = re.replace(&new_filename, "").into_owned();
+
+    // `span.file` only reflects static page content; APKPure kicks off the actual download a
+    // few seconds later via a client-side redirect. Wait for a file to land in `filepath` and
+    // its size to stop changing before tearing the tab down, or the transfer gets cut short.
+    wait_for_download_to_settle(&filepath, Duration::from_millis(500), Duration::from_secs(120));
+    let _ = tab.close_with_unload();
     Ok((filepath, new_filename, String::from(app_id)))
 }
 
+/// Polls `dir` until it contains a file whose size is unchanged across two consecutive
+/// checks (a simple stand-in for a download-complete signal), or until `timeout` elapses.
+fn wait_for_download_to_settle(dir: &str, poll_interval: Duration, timeout: Duration) {
+    let start = Instant::now();
+    let mut last_size = None;
+    loop {
+        let size = fs::read_dir(dir).ok().and_then(|mut entries| entries.next()).and_then(|entry| entry.ok()).and_then(|entry| entry.metadata().ok()).map(|meta| meta.len());
+
+        if let Some(size) = size {
+            if size > 0 && Some(size) == last_size {
+                return;
+            }
+            last_size = Some(size);
+        }
+
+        if start.elapsed() >= timeout {
+            return;
+        }
+        std::thread::sleep(poll_interval);
+    }
+}
+
+/// Resolves metadata for each app by navigating to its APKPure page and scraping the
+/// `span.file` filename/size, without ever setting a download behavior or saving a file.
+async fn print_apkpure_metadata(app_ids: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let browser = Arc::new(Browser::new(LaunchOptions::default_builder().headless(true).build()?)?);
+
+    for app_id in app_ids {
+        let browser = Arc::clone(&browser);
+        let app_id_for_task = app_id.clone();
+        let metadata = tokio::task::spawn_blocking(move || fetch_apkpure_metadata(&browser, &app_id_for_task)).await?;
+        match metadata {
+            Ok((filename, size)) => {
+                println!("{}", json!({
+                    "app_id": app_id,
+                    "version_code": serde_json::Value::Null,
+                    "size": size,
+                    "download_url": format!("https://apkpure.com/a/{}/download?from=details", app_id),
+                    "filename": filename,
+                    "source": "APKPure",
+                }));
+            }
+            Err(err) => {
+                eprintln!("Could not fetch metadata for {}: {}", app_id, err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Scrapes the `span.file` text from an app's APKPure page without triggering a download.
+fn fetch_apkpure_metadata(browser: &Browser, app_id: &str) -> Result<(String, String), Box<dyn Error + Send + Sync>> {
+    let app_url = format!("https://apkpure.com/a/{}", app_id);
+    let tab = browser.new_tab()?;
+    tab.navigate_to(&app_url)?;
+    tab.wait_until_navigated()?;
+    let elem_result = tab.wait_for_element("span.file")?;
+    let text = elem_result.get_inner_text()?;
+
+    let re = Regex::new(r"^(?P<filename>.*) \((?P<size>[0-9.]+ MB)\)$").unwrap();
+    let result = match re.captures(&text) {
+        Some(caps) => (caps["filename"].to_string(), caps["size"].to_string()),
+        None => (text, String::new()),
+    };
+    let _ = tab.close_with_unload();
+    Ok(result)
+}
+
 #[tokio::main]
-async fn main() -> WebDriverResult<()> {
+async fn main() -> Result<(), Box<dyn Error>> {
     let matches = App::new("APK Downloader")
         .author("William Budington <bill@eff.org>")
         .about("Downloads APKs from various sources")
@@ -217,14 +398,35 @@ async fn main() -> WebDriverResult<()> {
                 .takes_value(true)
                 .default_value("1")
                 .required_if("list_source", "CSV"))
+        .arg(
+            Arg::with_name("query")
+                .help("Search query to resolve app IDs from (required if list source is Search)")
+                .short("q")
+                .long("query")
+                .takes_value(true)
+                .required_if("list_source", "Search"))
+        .arg(
+            Arg::with_name("category")
+                .help("Play Store category to list top apps from (required if list source is Category)")
+                .long("category")
+                .takes_value(true)
+                .required_if("list_source", "Category"))
         .arg(
             Arg::with_name("app_name")
                 .help("Provide the name of an app directly")
                 .short("a")
                 .long("app-name")
                 .takes_value(true)
-                .conflicts_with("list_source")
-                .required_unless("list_source"))
+                .conflicts_with("list_source"))
+        .arg(
+            Arg::with_name("mode")
+                .help("What to do with the resolved app list")
+                .short("m")
+                .long("mode")
+                .takes_value(true)
+                .default_value("download")
+                .possible_values(&["download", "verify", "list-missing", "url"])
+                .required(false))
         .arg(
             Arg::with_name("download_source")
                 .help("Where to download the APKs from")
@@ -236,18 +438,18 @@ async fn main() -> WebDriverResult<()> {
                 .required(false))
         .arg(
             Arg::with_name("google_username")
-                .help("Google Username (required if download source is Google Play)")
+                .help("Google Username (required if download source is Google Play, or list source is Search/Category)")
                 .short("u")
                 .long("username")
                 .takes_value(true)
-                .required_if("download_source", "GooglePlay"))
+                .required_ifs(&[("download_source", "GooglePlay"), ("list_source", "Search"), ("list_source", "Category")]))
         .arg(
             Arg::with_name("google_password")
-                .help("Google App Password (required if download source is Google Play)")
+                .help("Google App Password (required if download source is Google Play, or list source is Search/Category)")
                 .short("p")
                 .long("password")
                 .takes_value(true)
-                .required_if("download_source", "GooglePlay"))
+                .required_ifs(&[("download_source", "GooglePlay"), ("list_source", "Search"), ("list_source", "Category")]))
         .arg(
             Arg::with_name("parallel")
                 .help("The number of parallel APK fetches to run at a time")
@@ -256,6 +458,20 @@ async fn main() -> WebDriverResult<()> {
                 .takes_value(true)
                 .default_value("4")
                 .required(false))
+        .arg(
+            Arg::with_name("retries")
+                .help("The number of attempts to make per app before giving up, including the first")
+                .long("retries")
+                .takes_value(true)
+                .default_value("3")
+                .required(false))
+        .arg(
+            Arg::with_name("retry_delay")
+                .help("The base delay, in milliseconds, before a retry (doubles every attempt)")
+                .long("retry-delay")
+                .takes_value(true)
+                .default_value("500")
+                .required(false))
         .arg(Arg::with_name("OUTPUT")
             .help("An absolute path to store output files")
             .required(true)
@@ -264,11 +480,28 @@ async fn main() -> WebDriverResult<()> {
 
     let download_source = value_t!(matches.value_of("download_source"), DownloadSource).unwrap();
     let parallel = value_t!(matches, "parallel", usize).unwrap();
+    let retries = value_t!(matches, "retries", u32).unwrap();
+    let retry_delay = Duration::from_millis(value_t!(matches, "retry_delay", u64).unwrap());
     let outpath = matches.value_of("OUTPUT").unwrap();
+    let mode = matches.value_of("mode").unwrap();
     if !Path::new(&outpath).is_dir() {
         println!("{}\n\nOUTPUT is not a valid directory", matches.usage());
         std::process::exit(1);
     };
+
+    if mode == "verify" {
+        return run_verify(outpath);
+    }
+
+    if matches.value_of("app_name").is_none() && matches.value_of("list_source").is_none() {
+        println!("{}\n\nEither -a or -l must be provided", matches.usage());
+        std::process::exit(1);
+    }
+
+    // Lazily logged in to on first use, and reused by the `GooglePlay` download/url paths
+    // below, so a run like `-l Search -d GooglePlay` only logs in to Google Play once.
+    let mut google_play_session: Option<Rc<Gpapi>> = None;
+
     let list = match matches.value_of("app_name") {
         Some(app_name) => vec![app_name.to_string()],
         None => {
@@ -290,19 +523,83 @@ async fn main() -> WebDriverResult<()> {
                         }
                     }
                 }
+                ListSource::Search => {
+                    let query = matches.value_of("query").unwrap();
+                    let username = matches.value_of("google_username").unwrap();
+                    let password = matches.value_of("google_password").unwrap();
+                    let gpa = ensure_google_play_session(&mut google_play_session, username, password).await;
+                    fetch_google_play_search_list(&gpa, query).await.unwrap()
+                }
+                ListSource::Category => {
+                    let category = matches.value_of("category").unwrap();
+                    let username = matches.value_of("google_username").unwrap();
+                    let password = matches.value_of("google_password").unwrap();
+                    let gpa = ensure_google_play_session(&mut google_play_session, username, password).await;
+                    fetch_google_play_category_list(&gpa, category).await.unwrap()
+                }
             }
         }
     };
 
+    if mode == "list-missing" {
+        for app_id in manifest::list_missing(&list, outpath) {
+            println!("{}", app_id);
+        }
+        return Ok(());
+    }
+
+    if mode == "url" {
+        match download_source {
+            DownloadSource::APKPure => print_apkpure_metadata(list).await?,
+            DownloadSource::GooglePlay => {
+                let username = matches.value_of("google_username").unwrap();
+                let password = matches.value_of("google_password").unwrap();
+                let gpa = ensure_google_play_session(&mut google_play_session, username, password).await;
+                print_google_play_metadata(list, &gpa).await?;
+            }
+        }
+        return Ok(());
+    }
+
     match download_source {
         DownloadSource::APKPure => {
-            download_apps_from_apkpure(list, parallel, outpath).await.unwrap();
+            download_apps_from_apkpure(list, parallel, outpath, retries, retry_delay).await.unwrap();
         },
         DownloadSource::GooglePlay => {
             let username = matches.value_of("google_username").unwrap();
             let password = matches.value_of("google_password").unwrap();
-            download_apps_from_google_play(list, parallel, username, password, outpath).await;
+            let gpa = ensure_google_play_session(&mut google_play_session, username, password).await;
+            download_apps_from_google_play(list, parallel, gpa, outpath, retries, retry_delay).await;
         },
     }
     Ok(())
 }
+
+/// Re-checks every entry in `outpath`'s manifest and reports missing/corrupt/size-mismatch
+/// files, exiting with a nonzero status if any entry failed verification.
+fn run_verify(outpath: &str) -> Result<(), Box<dyn Error>> {
+    let results = manifest::verify(outpath);
+    let mut failures = 0;
+    for (app_id, status) in &results {
+        match status {
+            VerifyStatus::Ok => println!("{}: ok", app_id),
+            VerifyStatus::Missing => {
+                println!("{}: missing", app_id);
+                failures += 1;
+            }
+            VerifyStatus::SizeMismatch => {
+                println!("{}: size mismatch", app_id);
+                failures += 1;
+            }
+            VerifyStatus::Corrupt => {
+                println!("{}: corrupt (hash mismatch)", app_id);
+                failures += 1;
+            }
+        }
+    }
+    println!("{}/{} entries verified ok", results.len() - failures, results.len());
+    if failures > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
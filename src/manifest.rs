@@ -0,0 +1,137 @@
+//! Tracks which APKs have been downloaded and lets a run be verified or resumed.
+//!
+//! Every successful download is recorded as a [`ManifestEntry`] in a `manifest.json`
+//! file living alongside the downloaded APKs in `OUTPUT`.  The `verify` and
+//! `list-missing` modes in `main` both read this file back to check the state of
+//! an `OUTPUT` directory without re-downloading anything.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub app_id: String,
+    pub filename: String,
+    pub size: u64,
+    pub sha256: String,
+    pub source: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    fn path(outpath: &str) -> PathBuf {
+        Path::new(outpath).join("manifest.json")
+    }
+
+    /// Loads `manifest.json` from `outpath`, or an empty manifest if it doesn't exist yet.
+    pub fn load(outpath: &str) -> Manifest {
+        match fs::read_to_string(Self::path(outpath)) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+            Err(_) => Manifest::default(),
+        }
+    }
+
+    pub fn save(&self, outpath: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(Self::path(outpath), json)
+    }
+
+    /// Records (or replaces) the entry for `app_id`.
+    pub fn record(&mut self, entry: ManifestEntry) {
+        self.entries.retain(|e| e.app_id != entry.app_id);
+        self.entries.push(entry);
+    }
+
+    pub fn get(&self, app_id: &str) -> Option<&ManifestEntry> {
+        self.entries.iter().find(|e| e.app_id == app_id)
+    }
+
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+}
+
+/// Builds the manifest entry for a freshly downloaded file and appends it to
+/// `manifest.json` in `outpath`, creating the manifest if it doesn't exist.
+pub fn record_download(
+    outpath: &str,
+    app_id: &str,
+    filename: &str,
+    source: &str,
+) -> io::Result<()> {
+    let full_path = Path::new(outpath).join(filename);
+    let size = fs::metadata(&full_path)?.len();
+    let sha256 = sha256_file(&full_path)?;
+
+    let mut manifest = Manifest::load(outpath);
+    manifest.record(ManifestEntry {
+        app_id: app_id.to_string(),
+        filename: filename.to_string(),
+        size,
+        sha256,
+        source: source.to_string(),
+    });
+    manifest.save(outpath)
+}
+
+/// Streams `path` through the hasher in chunks rather than reading it into memory whole, so
+/// hashing a large (or split `.xapk`) APK doesn't hold the entire file in memory at once.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum VerifyStatus {
+    Ok,
+    Missing,
+    SizeMismatch,
+    Corrupt,
+}
+
+/// Re-checks every entry in `outpath`'s manifest against the files actually on disk.
+pub fn verify(outpath: &str) -> Vec<(String, VerifyStatus)> {
+    let manifest = Manifest::load(outpath);
+    manifest
+        .entries()
+        .iter()
+        .map(|entry| {
+            let path = Path::new(outpath).join(&entry.filename);
+            let status = match fs::metadata(&path) {
+                Err(_) => VerifyStatus::Missing,
+                Ok(meta) if meta.len() != entry.size => VerifyStatus::SizeMismatch,
+                Ok(_) => match sha256_file(&path) {
+                    Ok(hash) if hash == entry.sha256 => VerifyStatus::Ok,
+                    _ => VerifyStatus::Corrupt,
+                },
+            };
+            (entry.app_id.clone(), status)
+        })
+        .collect()
+}
+
+/// Returns the subset of `app_ids` that don't have a valid (present, right-sized) file
+/// on disk according to the manifest, so a download run can be resumed.
+pub fn list_missing(app_ids: &[String], outpath: &str) -> Vec<String> {
+    let manifest = Manifest::load(outpath);
+    app_ids
+        .iter()
+        .filter(|app_id| match manifest.get(app_id) {
+            Some(entry) => match fs::metadata(Path::new(outpath).join(&entry.filename)) {
+                Ok(meta) => !meta.is_file() || meta.len() != entry.size,
+                Err(_) => true,
+            },
+            None => true,
+        })
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,48 @@
+//! Per-app progress reporting for parallel downloads, backed by `indicatif`.
+//!
+//! A single [`ProgressReporter`] is constructed per run and shared (via `Arc`) across every
+//! `buffer_unordered` task, so each in-flight download gets its own bar under one
+//! `MultiProgress` display instead of interleaving raw `println!` lines.
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct ProgressReporter {
+    multi: MultiProgress,
+}
+
+impl ProgressReporter {
+    pub fn new() -> Arc<ProgressReporter> {
+        Arc::new(ProgressReporter {
+            multi: MultiProgress::new(),
+        })
+    }
+
+    /// Adds an indeterminate spinner for `app_id`, for sources where the byte count isn't
+    /// known up front (e.g. a headless browser driving a third-party download page).
+    pub fn spinner(&self, app_id: &str) -> ProgressBar {
+        let bar = self.multi.add(ProgressBar::new_spinner());
+        bar.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} {prefix:.bold} {msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+        );
+        bar.set_prefix(app_id.to_string());
+        bar.set_message("downloading...");
+        bar.enable_steady_tick(Duration::from_millis(100));
+        bar
+    }
+}
+
+pub fn mark_retrying(bar: &ProgressBar, attempt: u32) {
+    bar.set_message(format!("retry #{}...", attempt));
+}
+
+pub fn mark_done(bar: &ProgressBar) {
+    bar.finish_with_message("done");
+}
+
+pub fn mark_failed(bar: &ProgressBar) {
+    bar.finish_with_message("failed");
+}
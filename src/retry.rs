@@ -0,0 +1,41 @@
+//! A single exponential-backoff retry loop shared by every download source, so the nested
+//! "try, retry #1, retry #2" blocks don't need to be hand-rolled per source.
+
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+/// Calls `op` up to `attempts` times total, sleeping `base_delay * 2^(n-1)` plus a little
+/// jitter between tries.  `is_retryable` decides whether a given error is worth retrying at
+/// all (e.g. a "file already exists" error should fail fast); `on_retry` is called with the
+/// upcoming attempt number (2, 3, ...) right before each retry's delay, so callers can surface
+/// retry state (e.g. update a progress bar) without threading it through the retry loop.
+pub async fn retry_with_backoff<T, E, F, Fut>(
+    attempts: u32,
+    base_delay: Duration,
+    is_retryable: impl Fn(&E) -> bool,
+    mut on_retry: impl FnMut(u32),
+    mut op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt >= attempts || !is_retryable(&err) => return Err(err),
+            Err(_) => {
+                on_retry(attempt + 1);
+                // Cap the exponent so a large --retries can't overflow 2u32.pow (panics in
+                // debug, wraps to 0 in release) -- 2^20 base delays is already an enormous wait.
+                let exponent = attempt.saturating_sub(1).min(20);
+                let backoff = base_delay * 2u32.pow(exponent);
+                let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..250));
+                tokio::time::sleep(backoff + jitter).await;
+                attempt += 1;
+            }
+        }
+    }
+}